@@ -1,11 +1,13 @@
 use clap::Parser;
+use futures::stream::{self, StreamExt};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::env;
+use std::path::{Path, PathBuf};
 use anyhow::{Result, Context, anyhow};
-use rust_xlsxwriter::{Workbook, Format, Color, Formula, Url};
+use rust_xlsxwriter::{Workbook, Worksheet, Format, Color, Formula, Url};
 
 #[derive(Parser)]
 #[command(
@@ -16,12 +18,234 @@ use rust_xlsxwriter::{Workbook, Format, Color, Formula, Url};
 struct Args {
     /// Financial Year (e.g., 2025)
     financial_year: Option<String>,
+
+    /// Path to the TOML config file (defaults to $XDG_CONFIG_HOME/paperless-to-spreadsheet.toml)
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Exchange rates endpoint returning `{"rates": {"USD": 0.66, ...}}` for --base-currency.
+    /// When unset, amounts are left in their original currency and no conversion is attempted.
+    #[arg(long, env = "EXCHANGE_RATES_URL")]
+    rates_url: Option<String>,
+
+    /// Currency that converted amounts are normalized to.
+    #[arg(long, env = "BASE_CURRENCY", default_value = "AUD")]
+    base_currency: String,
+
+    /// Minimum number of days a reused share link's expiry must still cover.
+    /// Existing links expiring within this many days (inclusive) are treated as unusable and replaced.
+    #[arg(long, default_value_t = 7)]
+    share_link_expiry_threshold_days: i64,
+
+    /// Preview the run (document counts per worksheet, share links that would be
+    /// created vs. reused) without creating share links or writing the .xlsx.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Maximum number of documents processed concurrently against the paperless API.
+    #[arg(long, default_value_t = 8)]
+    concurrency: usize,
+}
+
+/// On-disk TOML config describing how this paperless-ngx instance is organised:
+/// which custom fields and tags to use, and what worksheets to build.
+#[derive(Debug, Deserialize)]
+struct Config {
+    fields: FieldConfig,
+    tags: TagConfig,
+    /// Document-type name substring that marks a document as a statement
+    /// (statements get a blank amount and are sorted to the top of each worksheet).
+    #[serde(default = "default_statement_type_substring")]
+    statement_type_substring: String,
+    #[serde(default)]
+    worksheets: Vec<WorksheetConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FieldConfig {
+    /// Name of the custom field holding the financial year.
+    financial_year: String,
+    /// Name of the custom field holding the document amount.
+    amount: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagConfig {
+    /// Tag that all candidate documents are queried by.
+    primary: String,
+    /// Tag that marks a document as spanning multiple financial years.
+    #[serde(default)]
+    multi_fy: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct WorksheetConfig {
+    /// Worksheet name, also used as the `.xlsx` sheet tab name.
+    name: String,
+    /// Tag name documents must have to land on this worksheet. A worksheet
+    /// with no tag acts as the catch-all for documents matching no other
+    /// worksheet's tag.
+    #[serde(default)]
+    tag: Option<String>,
+    /// Ordered list of column keys to render. See `write_column` for the
+    /// supported keys.
+    #[serde(default = "default_columns")]
+    columns: Vec<String>,
+}
+
+fn default_statement_type_substring() -> String {
+    "statement".to_string()
+}
+
+fn default_columns() -> Vec<String> {
+    [
+        "title",
+        "counterparty",
+        "date",
+        "type",
+        "original_amount",
+        "original_currency",
+        "converted_amount",
+        "notes",
+        "share_link",
+        "link",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+/// Built-in config used when no config file is found at the resolved path,
+/// matching this tool's original hard-coded Work Expenses / Investment
+/// Property behaviour. As with that original behaviour, the `multi-fy` and
+/// `IP` tags are optional: if an instance doesn't have them, `resolve_config`
+/// warns and falls back to treating everything as a single-FY Work Expenses
+/// document rather than aborting the run.
+fn default_config() -> Config {
+    Config {
+        fields: FieldConfig {
+            financial_year: "Financial Year".to_string(),
+            amount: "Amount".to_string(),
+        },
+        tags: TagConfig {
+            primary: "Tax".to_string(),
+            multi_fy: Some("multi-fy".to_string()),
+        },
+        statement_type_substring: default_statement_type_substring(),
+        worksheets: vec![
+            WorksheetConfig {
+                name: "Work Expenses".to_string(),
+                tag: None,
+                columns: default_columns(),
+            },
+            WorksheetConfig {
+                name: "Investment Property".to_string(),
+                tag: Some("IP".to_string()),
+                columns: default_columns(),
+            },
+        ],
+    }
+}
+
+/// Resolves `$XDG_CONFIG_HOME/paperless-to-spreadsheet.toml`, falling back to
+/// `$HOME/.config/paperless-to-spreadsheet.toml`.
+fn default_config_path() -> Result<PathBuf> {
+    if let Ok(xdg_config_home) = env::var("XDG_CONFIG_HOME") {
+        return Ok(PathBuf::from(xdg_config_home).join("paperless-to-spreadsheet.toml"));
+    }
+
+    let home = env::var("HOME").context("Could not determine config directory: set XDG_CONFIG_HOME or HOME")?;
+    Ok(PathBuf::from(home).join(".config").join("paperless-to-spreadsheet.toml"))
+}
+
+fn load_config(path: &Path) -> Result<Config> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    toml::from_str(&contents)
+        .with_context(|| format!("Failed to parse config file {}", path.display()))
+}
+
+/// Config with field/tag names resolved to the IDs paperless actually uses.
+struct ResolvedConfig {
+    financial_year_field_id: u32,
+    amount_field_id: u32,
+    primary_tag_id: u32,
+    multi_fy_tag_id: Option<u32>,
+    statement_type_substring: String,
+    worksheets: Vec<ResolvedWorksheet>,
+}
+
+struct ResolvedWorksheet {
+    name: String,
+    tag_id: Option<u32>,
+    columns: Vec<String>,
+}
+
+fn resolve_config(config: Config, custom_fields: &[CustomField], tags: &[Tag]) -> Result<ResolvedConfig> {
+    let find_field = |name: &str| -> Result<u32> {
+        custom_fields
+            .iter()
+            .find(|field| field.name == name)
+            .map(|field| field.id)
+            .with_context(|| format!("Custom field '{}' not found", name))
+    };
+    let find_tag = |name: &str| -> Result<u32> {
+        tags.iter()
+            .find(|tag| tag.name == name)
+            .map(|tag| tag.id)
+            .with_context(|| format!("Tag '{}' not found", name))
+    };
+    // Unlike `find_tag`, a missing optional tag isn't fatal: the worksheet or
+    // multi-fy split it would have driven just falls back to catch-all
+    // behaviour, so we warn instead of aborting the whole run.
+    let find_tag_optional = |name: &str| -> Option<u32> {
+        match tags.iter().find(|tag| tag.name == name).map(|tag| tag.id) {
+            Some(id) => Some(id),
+            None => {
+                eprintln!("Warning: Tag '{}' not found; treating as absent", name);
+                None
+            }
+        }
+    };
+
+    let financial_year_field_id = find_field(&config.fields.financial_year)?;
+    let amount_field_id = find_field(&config.fields.amount)?;
+    let primary_tag_id = find_tag(&config.tags.primary)?;
+
+    let multi_fy_tag_id = config.tags.multi_fy.as_deref().and_then(find_tag_optional);
+
+    if config.worksheets.is_empty() {
+        return Err(anyhow!("Config must declare at least one [[worksheets]] entry"));
+    }
+
+    let worksheets = config
+        .worksheets
+        .into_iter()
+        .map(|ws| {
+            let tag_id = ws.tag.as_deref().and_then(find_tag_optional);
+            ResolvedWorksheet {
+                name: ws.name,
+                tag_id,
+                columns: ws.columns,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(ResolvedConfig {
+        financial_year_field_id,
+        amount_field_id,
+        primary_tag_id,
+        multi_fy_tag_id,
+        statement_type_substring: config.statement_type_substring,
+        worksheets,
+    })
 }
 
 #[derive(Debug, Deserialize)]
 struct CustomField {
     id: u32,
     name: String,
+    #[allow(dead_code)]
     data_type: String,
 }
 
@@ -99,36 +323,78 @@ struct ShareLinkResponse {
     slug: String,
 }
 
+/// An existing share link as returned by `GET /api/share_links/`.
+#[derive(Debug, Deserialize)]
+struct ShareLink {
+    document: u32,
+    slug: String,
+    expiration: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ShareLinksResponse {
+    results: Vec<ShareLink>,
+    next: Option<String>,
+}
+
 #[derive(Debug)]
 struct InvoiceData {
     title: String,
     counterparty: String,
     date: String,
     doc_type: String,
-    amount: String,
+    /// `None` for statements (blank amount) or when the amount couldn't be parsed.
+    amount: Option<f64>,
     currency: String,
+    /// `amount` converted to the base currency. `None` when no rate source is
+    /// configured, the document has no amount, or no rate was found for `currency`.
+    converted_amount: Option<f64>,
     notes: String,
     share_link: String,
+    /// Whether `share_link` reused an existing paperless share link rather
+    /// than creating a new one (or, in `--dry-run`, whether one would be reused).
+    share_link_reused: bool,
     link: String,
 }
 
-fn parse_amount_and_currency(raw_amount: &str) -> (String, String) {
+/// The amount used for cross-document totals, matching the rule `create_worksheet`
+/// uses for its own per-worksheet Total row when that worksheet has both an
+/// original and a converted amount column (as the default layout does): the
+/// base-currency conversion when
+/// conversion is enabled (blank, and excluded from the total, if this document's
+/// currency had no rate), otherwise the original amount.
+fn summary_amount(invoice: &InvoiceData, conversion_enabled: bool) -> Option<f64> {
+    if conversion_enabled {
+        invoice.converted_amount
+    } else {
+        invoice.amount
+    }
+}
+
+fn parse_amount_and_currency(raw_amount: &str) -> Result<(String, f64)> {
     let trimmed = raw_amount.trim();
 
     // Check if the string starts with a 3-letter currency code
-    if trimmed.len() >= 3 {
+    let (currency, amount_str) = if trimmed.len() >= 3 {
         let potential_currency = &trimmed[0..3];
 
         // Check if it's all alphabetic (currency code)
         if potential_currency.chars().all(|c| c.is_alphabetic()) {
-            let currency = potential_currency.to_uppercase();
-            let amount = &trimmed[3..];
-            return (currency, amount.to_string());
+            (potential_currency.to_uppercase(), trimmed[3..].trim())
+        } else {
+            ("AUD".to_string(), trimmed)
         }
-    }
+    } else {
+        ("AUD".to_string(), trimmed)
+    };
 
-    // No currency code found, default to AUD
-    ("AUD".to_string(), trimmed.to_string())
+    // Numbers may come through with thousands separators (e.g. "1,234.56")
+    let cleaned: String = amount_str.chars().filter(|c| !c.is_whitespace() && *c != ',').collect();
+    let amount: f64 = cleaned
+        .parse()
+        .with_context(|| format!("Failed to parse amount '{}'", raw_amount))?;
+
+    Ok((currency, amount))
 }
 
 fn get_expiry_date() -> String {
@@ -180,6 +446,138 @@ async fn create_share_link(
     Ok(format!("{}/share/{}", base_url, share_response.slug))
 }
 
+async fn get_share_links_for_document(
+    client: &Client,
+    base_url: &str,
+    api_key: &str,
+    document_id: u32,
+) -> Result<Vec<ShareLink>> {
+    let mut all_links = Vec::new();
+    let mut next_url = Some(format!("{}/api/share_links/?document={}", base_url, document_id));
+
+    while let Some(url) = next_url {
+        let response = client
+            .get(&url)
+            .header("Authorization", format!("Token {}", api_key))
+            .send()
+            .await
+            .context("Failed to fetch existing share links")?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to fetch existing share links for document {}: {}", document_id, response.status()));
+        }
+
+        let share_links_response: ShareLinksResponse = response
+            .json()
+            .await
+            .context("Failed to parse share links response")?;
+
+        all_links.extend(share_links_response.results);
+        next_url = share_links_response.next;
+    }
+
+    Ok(all_links)
+}
+
+/// Parses a share link `expiration` value, which paperless returns as an
+/// ISO-8601 datetime (e.g. `2025-08-01T00:00:00Z`), falling back to a bare
+/// `YYYY-MM-DD` date for robustness.
+fn parse_expiration_date(expiration: &str) -> Option<chrono::NaiveDate> {
+    chrono::DateTime::parse_from_rfc3339(expiration)
+        .map(|dt| dt.date_naive())
+        .ok()
+        .or_else(|| chrono::NaiveDate::parse_from_str(expiration, "%Y-%m-%d").ok())
+}
+
+/// Finds an existing share link whose expiry date is more than
+/// `expiry_threshold_days` away, i.e. safe to hand out again.
+fn find_reusable_share_link(links: &[ShareLink], expiry_threshold_days: i64) -> Option<&ShareLink> {
+    let cutoff = chrono::Utc::now().date_naive() + chrono::Duration::days(expiry_threshold_days);
+
+    links.iter().find(|link| {
+        match link.expiration.as_deref() {
+            // No expiration means the link never expires, so it's always reusable.
+            None => true,
+            Some(expiration) => parse_expiration_date(expiration)
+                .map(|expiration| expiration > cutoff)
+                .unwrap_or(false),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_reusable_link_with_datetime_expiration() {
+        let far_future = (chrono::Utc::now() + chrono::Duration::days(365))
+            .format("%Y-%m-%dT00:00:00Z")
+            .to_string();
+        let links = vec![ShareLink {
+            document: 1,
+            slug: "abc123".to_string(),
+            expiration: Some(far_future),
+        }];
+
+        let reusable = find_reusable_share_link(&links, 7);
+        assert_eq!(reusable.map(|link| link.slug.as_str()), Some("abc123"));
+    }
+
+    #[test]
+    fn rejects_link_expiring_within_threshold() {
+        let soon = (chrono::Utc::now() + chrono::Duration::days(1))
+            .format("%Y-%m-%dT00:00:00Z")
+            .to_string();
+        let links = vec![ShareLink {
+            document: 1,
+            slug: "abc123".to_string(),
+            expiration: Some(soon),
+        }];
+
+        assert!(find_reusable_share_link(&links, 7).is_none());
+    }
+
+    #[test]
+    fn reuses_link_with_no_expiration() {
+        let links = vec![ShareLink {
+            document: 1,
+            slug: "abc123".to_string(),
+            expiration: None,
+        }];
+
+        let reusable = find_reusable_share_link(&links, 7);
+        assert_eq!(reusable.map(|link| link.slug.as_str()), Some("abc123"));
+    }
+}
+
+/// Reuses a still-valid existing share link for `document_id` if one exists,
+/// otherwise creates a new one (unless `dry_run` is set, in which case it
+/// reports what it would have done without calling the create endpoint).
+/// Returns the share link URL (blank in dry-run if none exists yet) and
+/// whether an existing link was reused.
+async fn get_or_create_share_link(
+    client: &Client,
+    base_url: &str,
+    api_key: &str,
+    document_id: u32,
+    expiry_threshold_days: i64,
+    dry_run: bool,
+) -> Result<(String, bool)> {
+    let existing = get_share_links_for_document(client, base_url, api_key, document_id).await?;
+
+    if let Some(link) = find_reusable_share_link(&existing, expiry_threshold_days) {
+        return Ok((format!("{}/share/{}", base_url, link.slug), true));
+    }
+
+    if dry_run {
+        return Ok((String::new(), false));
+    }
+
+    let share_link = create_share_link(client, base_url, api_key, document_id).await?;
+    Ok((share_link, false))
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
@@ -191,9 +589,11 @@ async fn main() -> Result<()> {
             eprintln!("Error: Financial year is required\n");
             eprintln!("Queries paperless-ngx API to produce an Excel workbook of tax documents.\n");
             eprintln!("USAGE:");
-            eprintln!("    paperless-to-spreadsheet <FINANCIAL_YEAR>\n");
+            eprintln!("    paperless-to-spreadsheet <FINANCIAL_YEAR> [--config <PATH>]\n");
             eprintln!("ARGS:");
             eprintln!("    <FINANCIAL_YEAR>    Financial Year (e.g., 2025)\n");
+            eprintln!("OPTIONS:");
+            eprintln!("    --config <PATH>     Path to TOML config (default: $XDG_CONFIG_HOME/paperless-to-spreadsheet.toml)\n");
             eprintln!("ENVIRONMENT VARIABLES:");
             eprintln!("    PAPERLESS_URL       Paperless-ngx base URL (e.g., https://paperless.example.com)");
             eprintln!("    PAPERLESS_API_KEY   API token for authentication\n");
@@ -214,42 +614,37 @@ async fn main() -> Result<()> {
     let client = Client::new();
     let base_url = paperless_url.trim_end_matches('/');
 
-    // Step 1: Get custom field information to find the financial year field
-    let custom_fields = get_custom_fields(&client, base_url, &api_key).await?;
-    let financial_year_field = custom_fields.iter()
-        .find(|field| field.id == 1)
-        .context("Custom field with ID 1 (financial year) not found")?;
-
-    println!("Found financial year field: {} (ID: {})", financial_year_field.name, financial_year_field.id);
+    // Step 1: Load config, falling back to the built-in Work Expenses /
+    // Investment Property mapping if no config file exists.
+    let config_path = match args.config {
+        Some(path) => path,
+        None => default_config_path()?,
+    };
+    let config = if config_path.exists() {
+        println!("Loading config from {}", config_path.display());
+        load_config(&config_path)?
+    } else {
+        println!("No config file at {}, using built-in defaults", config_path.display());
+        default_config()
+    };
 
-    // Step 2: Get all tags to find the "Tax", "multi-fy", and "IP" tags
+    // Step 2: Get custom field and tag information and resolve the config's
+    // named fields/tags against them.
+    let custom_fields = get_custom_fields(&client, base_url, &api_key).await?;
     let tags = get_tags(&client, base_url, &api_key).await?;
+    let resolved = resolve_config(config, &custom_fields, &tags)?;
 
-    let tax_tag_id = tags.iter()
-        .find(|tag| tag.name == "Tax")
-        .map(|tag| tag.id)
-        .context("Tax tag not found. All documents should be tagged with 'Tax'")?;
-
-    println!("Found Tax tag with ID: {}", tax_tag_id);
-
-    let multi_fy_tag_id = tags.iter()
-        .find(|tag| tag.name == "multi-fy")
-        .map(|tag| tag.id);
-
-    if let Some(multi_fy_id) = multi_fy_tag_id {
-        println!("Found multi-fy tag with ID: {}", multi_fy_id);
-    } else {
-        println!("Warning: No 'multi-fy' tag found.");
+    println!("Using financial year field ID {} and amount field ID {}", resolved.financial_year_field_id, resolved.amount_field_id);
+    println!("Using primary tag ID {}", resolved.primary_tag_id);
+    match resolved.multi_fy_tag_id {
+        Some(id) => println!("Using multi-fy tag ID: {}", id),
+        None => println!("No multi-fy tag configured."),
     }
-
-    let ip_tag_id = tags.iter()
-        .find(|tag| tag.name == "IP")
-        .map(|tag| tag.id);
-
-    if let Some(ip_id) = ip_tag_id {
-        println!("Found IP tag with ID: {}", ip_id);
-    } else {
-        println!("Warning: No 'IP' tag found. All documents will be classified as Work Expenses.");
+    for ws in &resolved.worksheets {
+        match ws.tag_id {
+            Some(id) => println!("Worksheet '{}' filters on tag ID {}", ws.name, id),
+            None => println!("Worksheet '{}' is the catch-all worksheet", ws.name),
+        }
     }
 
     // Step 3: Get correspondents and document types for name lookup
@@ -258,27 +653,77 @@ async fn main() -> Result<()> {
 
     println!("Loaded {} correspondents and {} document types", correspondents.len(), document_types.len());
 
-    // Step 4: Get all documents tagged with "Tax" and filter them
-    let tax_documents = get_documents_by_tax_tag(&client, base_url, &api_key, tax_tag_id).await?;
-    println!("Found {} documents tagged with Tax", tax_documents.len());
+    // Step 4: Get all documents tagged with the configured primary tag
+    let tagged_documents = get_documents_by_tag(&client, base_url, &api_key, resolved.primary_tag_id).await?;
+    println!("Found {} documents tagged with the primary tag", tagged_documents.len());
 
     // Step 5: Filter documents to only include those that match our criteria
-    let filtered_documents = filter_documents_by_criteria(tax_documents, &fy, multi_fy_tag_id);
+    let filtered_documents = filter_documents_by_criteria(
+        tagged_documents,
+        &fy,
+        resolved.multi_fy_tag_id,
+        resolved.financial_year_field_id,
+    );
     println!("Filtered to {} documents for FY {}", filtered_documents.len(), fy);
 
-    // Step 6: Separate documents into work expenses and investment property
-    let (mut work_expenses, mut investment_property) = separate_documents_by_tag(filtered_documents, ip_tag_id, &correspondents, &document_types, base_url, &client, &api_key).await?;
+    // Step 5b: Fetch exchange rates if a rate source is configured; otherwise
+    // amounts stay in their original currency and the tool still works offline.
+    let exchange_rates = match &args.rates_url {
+        Some(rates_url) => {
+            let rates = fetch_exchange_rates(&client, rates_url, &args.base_currency).await?;
+            println!("Loaded {} exchange rates against base currency {}", rates.len(), args.base_currency);
+            Some(rates)
+        }
+        None => None,
+    };
+
+    // Step 6: Separate documents across the configured worksheets
+    let mut worksheet_data = separate_documents_by_worksheets(
+        filtered_documents,
+        &resolved,
+        &correspondents,
+        &document_types,
+        exchange_rates.as_ref(),
+        &args.base_currency,
+        args.share_link_expiry_threshold_days,
+        args.dry_run,
+        args.concurrency,
+        base_url,
+        &client,
+        &api_key,
+    ).await?;
 
     // Step 7: Sort documents with Statements first
-    sort_documents_by_type(&mut work_expenses);
-    sort_documents_by_type(&mut investment_property);
+    for data in &mut worksheet_data {
+        sort_documents_by_type(data, &resolved.statement_type_substring);
+    }
+
+    if args.dry_run {
+        println!("Dry run: no share links or .xlsx will be written. Would produce:");
+        for (ws, data) in resolved.worksheets.iter().zip(worksheet_data.iter()) {
+            let reused = data.iter().filter(|invoice| invoice.share_link_reused).count();
+            let created = data.len() - reused;
+            println!(
+                "  {}: {} documents ({} share links reused, {} would be created)",
+                ws.name, data.len(), reused, created
+            );
+        }
+        return Ok(());
+    }
 
-    println!("Work Expenses: {} documents", work_expenses.len());
-    println!("Investment Property: {} documents", investment_property.len());
+    for (ws, data) in resolved.worksheets.iter().zip(worksheet_data.iter()) {
+        println!("{}: {} documents", ws.name, data.len());
+    }
 
-    // Step 8: Create Excel spreadsheet with two worksheets
+    // Step 8: Create Excel spreadsheet with one worksheet per config entry
     let filename = format!("FY{} Documents.xlsx", fy);
-    create_excel_file_with_worksheets(&work_expenses, &investment_property, &filename)?;
+    create_excel_file_with_worksheets(
+        &resolved.worksheets,
+        &worksheet_data,
+        &args.base_currency,
+        exchange_rates.is_some(),
+        &filename,
+    )?;
 
     println!("Successfully created {}", filename);
 
@@ -307,6 +752,35 @@ async fn get_custom_fields(client: &Client, base_url: &str, api_key: &str) -> Re
     Ok(custom_fields_response.results)
 }
 
+#[derive(Debug, Deserialize)]
+struct ExchangeRatesResponse {
+    rates: HashMap<String, f64>,
+}
+
+/// Fetches today's rates for `base_currency`, where each rate is "1 base_currency = rate units
+/// of that currency" (e.g. exchangerate.host's `/latest?base=AUD` shape).
+async fn fetch_exchange_rates(client: &Client, rates_url: &str, base_currency: &str) -> Result<HashMap<String, f64>> {
+    let separator = if rates_url.contains('?') { '&' } else { '?' };
+    let url = format!("{}{}base={}", rates_url, separator, base_currency);
+
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .context("Failed to fetch exchange rates")?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to fetch exchange rates: {}", response.status()));
+    }
+
+    let rates_response: ExchangeRatesResponse = response
+        .json()
+        .await
+        .context("Failed to parse exchange rates response")?;
+
+    Ok(rates_response.rates)
+}
+
 async fn get_tags(client: &Client, base_url: &str, api_key: &str) -> Result<Vec<Tag>> {
     let url = format!("{}/api/tags/", base_url);
 
@@ -385,17 +859,17 @@ async fn get_document_types(client: &Client, base_url: &str, api_key: &str) -> R
     Ok(all_document_types)
 }
 
-async fn get_documents_by_tax_tag(
+async fn get_documents_by_tag(
     client: &Client,
     base_url: &str,
     api_key: &str,
-    tax_tag_id: u32
+    tag_id: u32
 ) -> Result<Vec<Document>> {
     let mut all_documents = Vec::new();
     let mut next_url = Some(format!(
         "{}/api/documents/?tags__id__in={}",
         base_url,
-        tax_tag_id
+        tag_id
     ));
 
     while let Some(url) = next_url {
@@ -425,7 +899,8 @@ async fn get_documents_by_tax_tag(
 fn filter_documents_by_criteria(
     documents: Vec<Document>,
     target_fy: &str,
-    multi_fy_tag_id: Option<u32>
+    multi_fy_tag_id: Option<u32>,
+    financial_year_field_id: u32,
 ) -> Vec<Document> {
     documents.into_iter().filter(|doc| {
         // Check if document has multi-fy tag
@@ -435,9 +910,9 @@ fn filter_documents_by_criteria(
             }
         }
 
-        // Check if document has the target financial year in custom field ID 1
+        // Check if document has the target financial year in the configured field
         for cf in &doc.custom_fields {
-            if cf.field == 1 { // Financial year field
+            if cf.field == financial_year_field_id {
                 let value_str = match &cf.value {
                     Value::String(s) => s.clone(),
                     Value::Number(n) => n.to_string(),
@@ -453,41 +928,78 @@ fn filter_documents_by_criteria(
     }).collect()
 }
 
-async fn separate_documents_by_tag(
+/// Assigns each document to the first worksheet whose tag it carries,
+/// falling back to the untagged (catch-all) worksheet if one is configured.
+/// Documents matching no worksheet at all are dropped.
+/// Processes all documents concurrently (up to `concurrency` in flight at
+/// once) and then partitions the results into worksheet buckets. Partitioning
+/// happens only after every result is collected, so the order documents
+/// complete in doesn't affect which bucket they land in; `sort_documents_by_type`
+/// restores a deterministic row order afterwards.
+async fn separate_documents_by_worksheets(
     documents: Vec<Document>,
-    ip_tag_id: Option<u32>,
+    resolved: &ResolvedConfig,
     correspondents: &[Correspondent],
     document_types: &[DocumentType],
+    exchange_rates: Option<&HashMap<String, f64>>,
+    base_currency: &str,
+    share_link_expiry_threshold_days: i64,
+    dry_run: bool,
+    concurrency: usize,
     base_url: &str,
     client: &Client,
     api_key: &str,
-) -> Result<(Vec<InvoiceData>, Vec<InvoiceData>)> {
-    let mut work_expenses = Vec::new();
-    let mut investment_property = Vec::new();
-
-    for doc in documents {
-        let invoice_data = process_single_document(doc, correspondents, document_types, base_url, client, api_key).await?;
-
-        // Check if document has IP tag
-        if let Some(ip_id) = ip_tag_id {
-            if invoice_data.0.tags.contains(&ip_id) {
-                investment_property.push(invoice_data.1);
-            } else {
-                work_expenses.push(invoice_data.1);
-            }
-        } else {
-            // No IP tag found, put everything in work expenses
-            work_expenses.push(invoice_data.1);
+) -> Result<Vec<Vec<InvoiceData>>> {
+    let mut buckets: Vec<Vec<InvoiceData>> = resolved.worksheets.iter().map(|_| Vec::new()).collect();
+
+    let results: Vec<Result<(Document, InvoiceData)>> = stream::iter(documents)
+        .map(|doc| {
+            process_single_document(
+                doc,
+                correspondents,
+                document_types,
+                resolved.amount_field_id,
+                &resolved.statement_type_substring,
+                exchange_rates,
+                base_currency,
+                share_link_expiry_threshold_days,
+                dry_run,
+                base_url,
+                client,
+                api_key,
+            )
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+
+    for result in results {
+        let (doc, invoice_data) = result?;
+
+        let matched = resolved.worksheets.iter().position(|ws| {
+            ws.tag_id.map(|id| doc.tags.contains(&id)).unwrap_or(false)
+        });
+
+        let target = matched.or_else(|| resolved.worksheets.iter().position(|ws| ws.tag_id.is_none()));
+
+        if let Some(index) = target {
+            buckets[index].push(invoice_data);
         }
     }
 
-    Ok((work_expenses, investment_property))
+    Ok(buckets)
 }
 
 async fn process_single_document(
     doc: Document,
     correspondents: &[Correspondent],
     document_types: &[DocumentType],
+    amount_field_id: u32,
+    statement_type_substring: &str,
+    exchange_rates: Option<&HashMap<String, f64>>,
+    base_currency: &str,
+    share_link_expiry_threshold_days: i64,
+    dry_run: bool,
     base_url: &str,
     client: &Client,
     api_key: &str,
@@ -529,25 +1041,60 @@ async fn process_single_document(
     };
 
     // Handle amount and currency based on document type
-    let (currency, amount) = if doc_type.to_lowercase().contains("statement") {
+    let (currency, amount) = if doc_type.to_lowercase().contains(statement_type_substring) {
         // Statements should have blank amounts
-        (String::new(), String::new())
+        (String::new(), None)
     } else {
-        // Get amount from custom field ID 3 (amount field) and parse currency
-        let raw_amount = custom_values.get(&3)
+        // Get amount from the configured amount field and parse currency
+        let raw_amount = custom_values.get(&amount_field_id)
             .map(|v| if v.is_empty() { "0" } else { v })
             .unwrap_or("0");
 
-        parse_amount_and_currency(raw_amount)
+        match parse_amount_and_currency(raw_amount) {
+            Ok((currency, amount)) => (currency, Some(amount)),
+            Err(e) => {
+                eprintln!("Warning: {} for document {}, leaving amount blank", e, doc.id);
+                (String::new(), None)
+            }
+        }
     };
 
-    // Create share link
-    println!("Creating share link for document: {}", doc.title);
-    let share_link = match create_share_link(client, base_url, api_key, doc.id).await {
-        Ok(link) => link,
+    // Convert to the base currency when a rate source is configured; otherwise
+    // leave the converted column blank so the tool still works offline.
+    let converted_amount = match (exchange_rates, amount) {
+        (Some(_), None) | (None, _) => None,
+        (Some(rates), Some(value)) => {
+            if currency.is_empty() || currency == base_currency {
+                Some(value)
+            } else {
+                match rates.get(&currency) {
+                    Some(rate) if *rate != 0.0 => Some(value / rate),
+                    _ => {
+                        eprintln!(
+                            "Warning: No exchange rate for currency '{}' on document {}, leaving converted amount blank",
+                            currency, doc.id
+                        );
+                        None
+                    }
+                }
+            }
+        }
+    };
+
+    // Reuse an existing non-expired share link if one is available, otherwise
+    // create one (or, in a dry run, just report that one would be created).
+    let (share_link, share_link_reused) = match get_or_create_share_link(
+        client,
+        base_url,
+        api_key,
+        doc.id,
+        share_link_expiry_threshold_days,
+        dry_run,
+    ).await {
+        Ok((link, reused)) => (link, reused),
         Err(e) => {
-            eprintln!("Warning: Failed to create share link for document {}: {}", doc.id, e);
-            String::new() // Use empty string if share link creation fails
+            eprintln!("Warning: Failed to get or create share link for document {}: {}", doc.id, e);
+            (String::new(), false) // Use empty string if share link creation fails
         }
     };
 
@@ -564,31 +1111,101 @@ async fn process_single_document(
         doc_type,
         amount,
         currency,
+        converted_amount,
         notes,
         share_link,
+        share_link_reused,
         link,
     };
 
     Ok((doc, invoice_data))
 }
 
-fn sort_documents_by_type(documents: &mut Vec<InvoiceData>) {
+/// Sorts statements first, then by date/title. Documents are processed
+/// concurrently, so relying on input order for a stable secondary sort no
+/// longer gives deterministic output; sorting explicitly on (date, title) does.
+fn sort_documents_by_type(documents: &mut Vec<InvoiceData>, statement_type_substring: &str) {
     documents.sort_by(|a, b| {
-        let a_is_statement = a.doc_type.to_lowercase().contains("statement");
-        let b_is_statement = b.doc_type.to_lowercase().contains("statement");
+        let a_is_statement = a.doc_type.to_lowercase().contains(statement_type_substring);
+        let b_is_statement = b.doc_type.to_lowercase().contains(statement_type_substring);
 
         match (a_is_statement, b_is_statement) {
             (true, false) => std::cmp::Ordering::Less,    // Statements first
             (false, true) => std::cmp::Ordering::Greater, // Non-statements after
-            _ => std::cmp::Ordering::Equal,               // Keep existing order within same type
+            _ => (&a.date, &a.title).cmp(&(&b.date, &b.title)),
         }
     });
 }
 
+/// Writes a single column's value for one row. Unknown column keys are
+/// skipped rather than treated as an error, so a typo in a worksheet's
+/// `columns` list just omits that column.
+fn write_column(worksheet: &mut Worksheet, row: u32, col: u16, key: &str, invoice: &InvoiceData, currency_format: &Format) -> Result<()> {
+    match key {
+        "title" => { worksheet.write_string(row, col, &invoice.title)?; }
+        "counterparty" => { worksheet.write_string(row, col, &invoice.counterparty)?; }
+        "date" => { worksheet.write_string(row, col, &invoice.date)?; }
+        "type" => { worksheet.write_string(row, col, &invoice.doc_type)?; }
+        "amount" | "original_amount" => {
+            if let Some(amount) = invoice.amount {
+                worksheet.write_number_with_format(row, col, amount, currency_format)?;
+            }
+        }
+        "currency" | "original_currency" => { worksheet.write_string(row, col, &invoice.currency)?; }
+        "converted_amount" => {
+            if let Some(amount) = invoice.converted_amount {
+                worksheet.write_number_with_format(row, col, amount, currency_format)?;
+            }
+        }
+        "notes" => { worksheet.write_string(row, col, &invoice.notes)?; }
+        "share_link" => {
+            if !invoice.share_link.is_empty() {
+                worksheet.write_url_with_text(row, col, Url::new(&invoice.share_link), "Share Link")?;
+            }
+        }
+        "link" => { worksheet.write_url_with_text(row, col, Url::new(&invoice.link), "Link")?; }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Converts a 0-based column index to its Excel column letters (0 -> "A", 26 -> "AA").
+fn column_letter(mut col: u16) -> String {
+    let mut letters = Vec::new();
+    loop {
+        letters.push((b'A' + (col % 26) as u8) as char);
+        if col < 26 {
+            break;
+        }
+        col = col / 26 - 1;
+    }
+    letters.iter().rev().collect()
+}
+
+fn column_header(key: &str, base_currency: &str) -> String {
+    match key {
+        "title" => "Title".to_string(),
+        "counterparty" => "Counterparty".to_string(),
+        "date" => "Date".to_string(),
+        "type" => "Type".to_string(),
+        "amount" | "original_amount" => "Original Amount".to_string(),
+        "currency" | "original_currency" => "Original Currency".to_string(),
+        "converted_amount" => format!("Amount ({})", base_currency),
+        "notes" => "Notes".to_string(),
+        "share_link" => "Share Link".to_string(),
+        "link" => "Link".to_string(),
+        other => other.to_string(),
+    }
+}
+
 fn create_worksheet(
     workbook: &mut Workbook,
     data: &[InvoiceData],
-    worksheet_name: &str
+    worksheet_name: &str,
+    columns: &[String],
+    base_currency: &str,
+    conversion_enabled: bool,
 ) -> Result<()> {
     let worksheet = workbook.add_worksheet().set_name(worksheet_name)?;
 
@@ -596,27 +1213,44 @@ fn create_worksheet(
     let header_format = Format::new()
         .set_bold()
         .set_background_color(Color::RGB(0xD3D3D3));
+    let total_label_format = Format::new().set_bold();
+    let currency_format = Format::new().set_num_format("#,##0.00");
 
-    // Write headers - Title, Counterparty, Date, Type, Amount, Currency, Notes, Share Link, Link
-    let headers = ["Title", "Counterparty", "Date", "Type", "Amount", "Currency", "Notes", "Share Link", "Link"];
-    for (col, &header) in headers.iter().enumerate() {
-        worksheet.write_string_with_format(0, col as u16, header, &header_format)?;
+    // Write headers according to the configured column layout
+    for (col, key) in columns.iter().enumerate() {
+        worksheet.write_string_with_format(0, col as u16, &column_header(key, base_currency), &header_format)?;
     }
 
     // Write data
     for (row, invoice) in data.iter().enumerate() {
         let row_num = (row + 1) as u32;
-        worksheet.write_string(row_num, 0, &invoice.title)?;
-        worksheet.write_string(row_num, 1, &invoice.counterparty)?;
-        worksheet.write_string(row_num, 2, &invoice.date)?;
-        worksheet.write_string(row_num, 3, &invoice.doc_type)?;
-        worksheet.write_string(row_num, 4, &invoice.amount)?;
-        worksheet.write_string(row_num, 5, &invoice.currency)?;
-        worksheet.write_string(row_num, 6, &invoice.notes)?;
-        if !invoice.share_link.is_empty() {
-            worksheet.write_url_with_text(row_num, 7, Url::new(&invoice.share_link), "Share Link")?;
+        for (col, key) in columns.iter().enumerate() {
+            write_column(worksheet, row_num, col as u16, key, invoice, &currency_format)?;
+        }
+    }
+
+    // Append a SUM totals row over the amount column, if this worksheet has one.
+    // Prefer the base-currency converted column so mixed-currency worksheets
+    // still sum to something meaningful, but only when a conversion was
+    // actually attempted — otherwise "converted_amount" is blank for every
+    // row and summing it would total to zero while the real values sit in
+    // "Original Amount". Statements leave their amount cell blank, so SUM
+    // naturally skips them.
+    let amount_col = if conversion_enabled {
+        columns.iter().position(|key| key == "converted_amount")
+            .or_else(|| columns.iter().position(|key| key == "amount" || key == "original_amount"))
+    } else {
+        columns.iter().position(|key| key == "amount" || key == "original_amount")
+            .or_else(|| columns.iter().position(|key| key == "converted_amount"))
+    };
+    if let Some(amount_col) = amount_col {
+        if !data.is_empty() {
+            let total_row = (data.len() + 1) as u32;
+            worksheet.write_string_with_format(total_row, 0, "Total", &total_label_format)?;
+            let column = column_letter(amount_col as u16);
+            let formula = Formula::new(format!("=SUM({0}2:{0}{1})", column, data.len() + 1));
+            worksheet.write_formula_with_format(total_row, amount_col as u16, &formula, &currency_format)?;
         }
-        worksheet.write_url_with_text(row_num, 8, Url::new(&invoice.link), "Link")?;
     }
 
     // Auto-fit columns
@@ -625,21 +1259,89 @@ fn create_worksheet(
     Ok(())
 }
 
+/// Builds the Summary worksheet: totals per worksheet (category), per
+/// document type, and per correspondent across all worksheets combined.
+fn create_summary_worksheet(
+    workbook: &mut Workbook,
+    worksheets: &[ResolvedWorksheet],
+    data: &[Vec<InvoiceData>],
+    conversion_enabled: bool,
+) -> Result<()> {
+    let worksheet = workbook.add_worksheet().set_name("Summary")?;
+
+    let header_format = Format::new()
+        .set_bold()
+        .set_background_color(Color::RGB(0xD3D3D3));
+    let currency_format = Format::new().set_num_format("#,##0.00");
+
+    let mut row = 0u32;
+    let mut write_section = |row: &mut u32, title: &str, totals: &[(String, f64)]| -> Result<()> {
+        worksheet.write_string_with_format(*row, 0, title, &header_format)?;
+        worksheet.write_string_with_format(*row, 1, "Total", &header_format)?;
+        *row += 1;
+        for (label, total) in totals {
+            worksheet.write_string(*row, 0, label)?;
+            worksheet.write_number_with_format(*row, 1, *total, &currency_format)?;
+            *row += 1;
+        }
+        *row += 1;
+        Ok(())
+    };
+
+    let by_category: Vec<(String, f64)> = worksheets
+        .iter()
+        .zip(data.iter())
+        .map(|(ws, rows)| {
+            (
+                ws.name.clone(),
+                rows.iter().filter_map(|invoice| summary_amount(invoice, conversion_enabled)).sum(),
+            )
+        })
+        .collect();
+    write_section(&mut row, "Category", &by_category)?;
+
+    let mut by_type: HashMap<String, f64> = HashMap::new();
+    let mut by_correspondent: HashMap<String, f64> = HashMap::new();
+    for rows in data {
+        for invoice in rows {
+            if let Some(amount) = summary_amount(invoice, conversion_enabled) {
+                *by_type.entry(invoice.doc_type.clone()).or_insert(0.0) += amount;
+                *by_correspondent.entry(invoice.counterparty.clone()).or_insert(0.0) += amount;
+            }
+        }
+    }
+
+    let mut by_type: Vec<(String, f64)> = by_type.into_iter().collect();
+    by_type.sort_by(|a, b| a.0.cmp(&b.0));
+    write_section(&mut row, "Document Type", &by_type)?;
+
+    let mut by_correspondent: Vec<(String, f64)> = by_correspondent.into_iter().collect();
+    by_correspondent.sort_by(|a, b| a.0.cmp(&b.0));
+    write_section(&mut row, "Correspondent", &by_correspondent)?;
+
+    worksheet.autofit();
+
+    Ok(())
+}
+
 fn create_excel_file_with_worksheets(
-    work_expenses: &[InvoiceData],
-    investment_property: &[InvoiceData],
-    filename: &str
+    worksheets: &[ResolvedWorksheet],
+    data: &[Vec<InvoiceData>],
+    base_currency: &str,
+    conversion_enabled: bool,
+    filename: &str,
 ) -> Result<()> {
     let mut workbook = Workbook::new();
 
-    // Create Work Expenses worksheet
-    create_worksheet(&mut workbook, work_expenses, "Work Expenses")?;
-
-    // Create Investment Property worksheet (only if there are IP documents)
-    if !investment_property.is_empty() {
-        create_worksheet(&mut workbook, investment_property, "Investment Property")?;
+    for (index, (ws, rows)) in worksheets.iter().zip(data.iter()).enumerate() {
+        // Always create the first worksheet even if empty; skip later ones with no rows.
+        if index == 0 || !rows.is_empty() {
+            create_worksheet(&mut workbook, rows, &ws.name, &ws.columns, base_currency, conversion_enabled)?;
+        }
     }
 
+    create_summary_worksheet(&mut workbook, worksheets, data, conversion_enabled)?;
+
     workbook.save(filename)?;
     Ok(())
 }